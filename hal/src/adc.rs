@@ -35,6 +35,51 @@ const VREFINT_CAL_VREF: u16 = 3000; // mV
 const TS_CAL1_TEMP: u16 = 30; // °C
 const TS_CAL2_TEMP: u16 = 130; // °C
 
+/// Coin cell discharge curve as (voltage mV, state of charge %) break points, ordered from full
+/// to empty. A CR2032 holds ~3.0 V when full, sags to ~2.9 V around the half way point and is
+/// considered flat by ~2.5 V.
+const DISCHARGE_CURVE: [(u16, u8); 3] = [(3000, 100), (2900, 50), (2500, 0)];
+
+/// ADC hardware oversampling ratio.
+///
+/// The oversampler accumulates `ratio` conversions and right-shifts the result back into the
+/// 12-bit data register, giving hardware averaging with no extra CPU cycles.
+#[derive(Clone, Copy)]
+pub enum Oversampling {
+    X2,
+    X4,
+    X8,
+    X16,
+    X32,
+    X64,
+    X128,
+    X256,
+}
+
+impl Oversampling {
+    /// The `OVSR[2:0]` ratio selection field value
+    const fn ovsr(self) -> u8 {
+        self as u8
+    }
+
+    /// The `OVSS[3:0]` right-shift needed to return the accumulated result to 12 bits
+    const fn ovss(self) -> u8 {
+        // The accumulator grows by one bit per doubling of the ratio
+        self as u8 + 1
+    }
+}
+
+/// A die temperature reading in millidegrees celsius
+pub struct Temperature(i32);
+
+impl Deref for Temperature {
+    type Target = i32;
+
+    fn deref(&self) -> &i32 {
+        &self.0
+    }
+}
+
 /// The results of an ADC measurement
 pub struct AdcMeasurement {
     vrefint: u16,
@@ -49,16 +94,43 @@ impl AdcMeasurement {
         (VREFINT_CAL_VREF * *vrefint_cal) / self.vrefint
     }
 
-    /// Get the temperature in degrees celsius
+    /// Estimate the battery state of charge as a percentage (0-100)
+    ///
+    /// The measured cell voltage is mapped through the [`DISCHARGE_CURVE`] with linear
+    /// interpolation between break points.
+    pub unsafe fn state_of_charge(&self) -> u8 {
+        let voltage = self.voltage();
+
+        // Above the first break point the cell is full, below the last it is flat.
+        if voltage >= DISCHARGE_CURVE[0].0 {
+            return DISCHARGE_CURVE[0].1;
+        }
+
+        for pair in DISCHARGE_CURVE.windows(2) {
+            let (v_hi, soc_hi) = pair[0];
+            let (v_lo, soc_lo) = pair[1];
+
+            if voltage >= v_lo {
+                // Linearly interpolate between the two surrounding break points
+                return soc_lo + ((soc_hi - soc_lo) as u16 * (voltage - v_lo) / (v_hi - v_lo)) as u8;
+            }
+        }
+
+        0
+    }
+
+    /// Get the die temperature in millidegrees celsius
     ///
-    pub unsafe fn temperature(&self) -> u16 {
-        // FIXME: Make this millidegrees
+    pub unsafe fn temperature(&self) -> Temperature {
         let ts_cal1 = 0x1FF8007A as *const u16;
         let ts_cal2 = 0x1FF8007E as *const u16;
 
-        let gradient = (TS_CAL2_TEMP - TS_CAL1_TEMP) / (*ts_cal2 - *ts_cal1);
+        // Work in millidegrees throughout so the oversampled reading keeps its sub-degree
+        // resolution instead of being truncated by an integer gradient.
+        let span = (TS_CAL2_TEMP - TS_CAL1_TEMP) as i32 * 1000;
+        let gradient = span / (*ts_cal2 as i32 - *ts_cal1 as i32);
 
-        gradient * (self.tsense - *ts_cal1) + TS_CAL1_TEMP
+        Temperature(gradient * (self.tsense as i32 - *ts_cal1 as i32) + TS_CAL1_TEMP as i32 * 1000)
     }
 }
 
@@ -85,11 +157,26 @@ impl DerefMut for Adc {
 
 impl Adc {
     /// Configure the ADC
-    pub fn configure(adc: ADC, sys: &mut System, syscfg: &mut SYSCFG) -> Self {
+    ///
+    /// `oversampling` selects the hardware averaging ratio used for every conversion, trading
+    /// throughput for a lower noise floor.
+    pub fn configure(adc: ADC, sys: &mut System, syscfg: &mut SYSCFG, oversampling: Oversampling) -> Self {
         sys.enable_adc_clk();
 
-        // Use PCLK/2 as the ADC clock
-        adc.cfgr2.write(|w| w.ckmode().pclk_div2());
+        // Configure the ADC configuration register 2
+        //
+        // * Use PCLK/2 as the ADC clock
+        // * Enable the oversampler with the requested ratio and matching right-shift
+        adc.cfgr2.write(|w| {
+            w.ckmode()
+                .pclk_div2()
+                .ovse()
+                .enabled()
+                .ovsr()
+                .bits(oversampling.ovsr())
+                .ovss()
+                .bits(oversampling.ovss())
+        });
 
         // Enable low frequency mode as PCLK is <3.5 MHz
         adc.ccr.write(|w| w.lfmen().enabled());
@@ -1,23 +1,44 @@
 use crate::system::{System, CLK_FREQ};
 use core::marker::PhantomData;
+use fugit::Hertz;
 use stm32l0::stm32l0x3::{GPIOA, TIM2};
 
 // Timer prescaler value to give a 1 Hz tick
 const PRESCALER: u16 = (CLK_FREQ - 1) as u16;
 
-/// Calculate the value for the auto reload register from a frequency (Hz)
+/// A duty cycle expressed as a percentage (0-100)
+#[derive(Clone, Copy)]
+pub struct Percent(pub u8);
+
+/// Calculate the value for the auto reload register from a raw frequency (Hz)
 ///
-/// See [`Buzzer::arr()`] for usage information.
-pub const fn arr_from_frequency(freq: usize) -> u16 {
+/// This is the `const fn` form intended for compile-time ARR constants; see
+/// [`arr_from_frequency`] for the [`Hertz`] based runtime form.
+pub const fn arr_from_raw_frequency(freq: usize) -> u16 {
     ((CLK_FREQ / (freq * (PRESCALER as usize + 1))) - 1) as u16
 }
 
-/// Calculate the value for the compare capture register from a duty cycle (%) and AAR value
+/// Calculate the value for the auto reload register from a frequency
+///
+/// See [`Buzzer::arr()`] for usage information.
+pub fn arr_from_frequency(freq: Hertz<u32>) -> u16 {
+    arr_from_raw_frequency(freq.to_Hz() as usize)
+}
+
+/// Calculate the value for the compare capture register from a raw duty cycle (%) and ARR value
+///
+/// This is the `const fn` form intended for compile-time CCR constants; see
+/// [`ccr_from_duty`] for the [`Percent`] based runtime form.
+pub const fn ccr_from_raw_duty(duty: usize, arr: u16) -> u16 {
+    (duty as u16 * arr / 100) as u16
+}
+
+/// Calculate the value for the compare capture register from a duty cycle and ARR value
 /// ([`arr_from_frequency`])
 ///
 /// See [`Buzzer::ccr()`] for usage information.
-pub const fn ccr_from_duty(duty: usize, aar: u16) -> u16 {
-    (duty as u16 * aar / 100) as u16
+pub fn ccr_from_duty(duty: Percent, arr: u16) -> u16 {
+    ccr_from_raw_duty(duty.0 as usize, arr)
 }
 
 pub struct Running;
@@ -92,9 +113,9 @@ impl<S> Buzzer<S> {
     ///
     /// ```rust
     /// // Calculate a buzzer frequency of 1 kHz
-    /// const BUZZER_FREQ = arr_from_frequency(1000);
+    /// const BUZZER_FREQ: u16 = arr_from_raw_frequency(1000);
     ///
-    /// buzzer.aar(BUZZER_FREQ);
+    /// buzzer.arr(BUZZER_FREQ);
     /// ```
     pub fn arr(&mut self, arr: u16) {
         self.0.arr.write(|w| w.arr().bits(arr));
@@ -110,14 +131,91 @@ impl<S> Buzzer<S> {
     ///
     /// ```rust
     /// // Calculate a buzzer frequency of 1 kHz
-    /// const BUZZER_FREQ: u16 = arr_from_frequency(1000);
+    /// const BUZZER_FREQ: u16 = arr_from_raw_frequency(1000);
     ///
     /// // Calculate a buzzer duty cycle of 50%
-    /// const BUZZER_DUTY: u16 = ccr_from_duty(50, BUZZER_FREQ);
+    /// const BUZZER_DUTY: u16 = ccr_from_raw_duty(50, BUZZER_FREQ);
     ///
-    /// buzzer.crr(BUZZER_DUTY);
+    /// buzzer.ccr(BUZZER_DUTY);
     /// ```
-    pub fn ccr(&mut self, arr: u16) {
-        self.0.arr.write(|w| w.arr().bits(arr));
+    pub fn ccr(&mut self, ccr: u16) {
+        self.0.ccr1.write(|w| w.ccr().bits(ccr as u32));
+    }
+}
+
+/// A single step of a melody: a tone of the given frequency and duty cycle held for a duration.
+pub struct Note {
+    /// The tone frequency
+    pub frequency: Hertz<u32>,
+    /// How long the tone is held
+    pub duration: fugit::MillisDurationU32,
+    /// The PWM duty cycle of the tone
+    pub duty: Percent,
+}
+
+/// The result of advancing a [`Melody`]
+pub enum Melody {
+    /// The melody is still playing; call [`Player::advance`] again after the current note's
+    /// duration has elapsed.
+    Playing(Player),
+    /// The melody has finished and the buzzer has returned to [`Stopped`].
+    Finished(Buzzer<Stopped>),
+}
+
+/// A non-blocking melody player layered on the PWM channel.
+///
+/// Rather than blocking for each note, the player reprograms the timer for the current note and
+/// hands back how long that note should sound. The application schedules the next
+/// [`advance`](Player::advance) on the monotonic (see [`crate::mono`]), so the alarm can beep a
+/// recognizable pattern without busy-waiting.
+pub struct Player {
+    buzzer: Buzzer<Running>,
+    notes: &'static [Note],
+    index: usize,
+}
+
+impl Buzzer<Stopped> {
+    /// Start playing a melody, programming the first note.
+    ///
+    /// Returns [`Melody::Playing`] with a [`Player`]; query [`Player::duration`] for how long the
+    /// current note should sound, then pass the player back to [`Player::advance`] once that
+    /// duration has elapsed.
+    pub fn play(self, notes: &'static [Note]) -> Melody {
+        let mut player = Player {
+            buzzer: self.start(),
+            notes,
+            index: 0,
+        };
+
+        player.program();
+        Melody::Playing(player)
+    }
+}
+
+impl Player {
+    /// Program the timer for the note at the current index.
+    fn program(&mut self) {
+        let note = &self.notes[self.index];
+        let arr = arr_from_frequency(note.frequency);
+
+        self.buzzer.arr(arr);
+        self.buzzer.ccr(ccr_from_duty(note.duty, arr));
+    }
+
+    /// How long the current note should sound for.
+    pub fn duration(&self) -> fugit::MillisDurationU32 {
+        self.notes[self.index].duration
+    }
+
+    /// Advance to the next note, stopping the buzzer once the sequence ends.
+    pub fn advance(mut self) -> Melody {
+        self.index += 1;
+
+        if self.index >= self.notes.len() {
+            return Melody::Finished(self.buzzer.stop());
+        }
+
+        self.program();
+        Melody::Playing(self)
     }
 }
@@ -1,43 +1,71 @@
+//! # 7 segment font
+//!
+//! Maps glyphs (the hexadecimal digits `0`-`F`, a minus sign and a blank) onto the physical
+//! segments of each of the six 7 segment displays. See [`crate::lcd::segment`] for the segment
+//! layout and [`Lcd`](crate::lcd::Lcd) for the high level writing API built on top of this.
+
 use crate::lcd::segment::*;
-use paste::paste;
-
-macro_rules! digits {
-    ($($name:ident => $segs:expr),*) => {
-        $(
-            pub const $name: Segments = $segs;
-        )*
-    };
-}
 
-macro_rules! segments {
-    ($digit:expr; $($seg:ident),*) => {
-        paste! {
-            {
-                let mut res = 0;
-                $(
-                    res |= [<D $digit _ $seg>];
-                )*
-                res
-            }
+/// Segment masks for the hexadecimal glyphs `0`-`F`, ordered `A` (bit 0) to `G` (bit 6).
+const FONT: [u8; 16] = [
+    0b0111111, // 0
+    0b0000110, // 1
+    0b1011011, // 2
+    0b1001111, // 3
+    0b1100110, // 4
+    0b1101101, // 5
+    0b1111101, // 6
+    0b0000111, // 7
+    0b1111111, // 8
+    0b1101111, // 9
+    0b1110111, // A
+    0b1111100, // b
+    0b0111001, // C
+    0b1011110, // d
+    0b1111001, // E
+    0b1110001, // F
+];
+
+/// Segment mask for a minus sign (the middle segment `G`)
+const MINUS_MASK: u8 = 0b1000000;
+
+/// The `A`-`G` segment constants for each of the six physical digit positions
+const POSITIONS: [[Segments; 7]; 6] = [
+    [D0_A, D0_B, D0_C, D0_D, D0_E, D0_F, D0_G],
+    [D1_A, D1_B, D1_C, D1_D, D1_E, D1_F, D1_G],
+    [D2_A, D2_B, D2_C, D2_D, D2_E, D2_F, D2_G],
+    [D3_A, D3_B, D3_C, D3_D, D3_E, D3_F, D3_G],
+    [D4_A, D4_B, D4_C, D4_D, D4_E, D4_F, D4_G],
+    [D5_A, D5_B, D5_C, D5_D, D5_E, D5_F, D5_G],
+];
+
+/// Build the segments for a glyph mask at a physical position.
+const fn render(pos: usize, mask: u8) -> Segments {
+    let segs = POSITIONS[pos];
+
+    let mut res = BLANK;
+    let mut seg = 0;
+    while seg < 7 {
+        if mask & (1 << seg) != 0 {
+            res |= segs[seg];
         }
-    };
-}
+        seg += 1;
+    }
 
-pub const fn digit(digit: u32, seg: u32) -> Segments {
-    match digit {
-        0 => segments!(digit; A, B, C, D, E, F),
-        1 => segments!()
-    } 
+    res
 }
 
-digits! {
-    D0_0 => D0_AD | D0_B | D0_C | D0_E | D0_F,
-    D0_1 => D0_B | D0_C,
-    D0_2 => D0_AD | D0_B | D0_E | D0_G,
-    D0_3 => D0_AD | D0_B | D0_C | D0_G,
-    D0_4 => D0_F | D0_B | D0_G | D0_C,
-    D0_5 => D0_AD | D0_F | D0_G | D0_C,
+/// The segments of a hexadecimal digit (`0`-`F`) at a physical position.
+pub const fn digit(pos: usize, value: u8) -> Segments {
+    render(pos, FONT[value as usize])
+}
 
-    D1_0 => D1_A | D1_B | D1_C | D1_D | D1_E | D1_F,
+/// A minus sign at a physical position.
+pub const fn minus(pos: usize) -> Segments {
+    render(pos, MINUS_MASK)
+}
 
-}
\ No newline at end of file
+/// Every segment of a physical position, used to clear it before writing a new glyph.
+pub const fn mask(pos: usize) -> Segments {
+    render(pos, 0b1111111)
+}
@@ -3,9 +3,12 @@ pub mod segment;
 
 use core::ops::{Deref, DerefMut};
 use crate::system::System;
-use self::segment::Segments;
+use self::segment::{Segments, BATTERY, BLANK};
 use stm32l0::stm32l0x3::{GPIOA, GPIOB, LCD, SYSCFG};
 
+/// Mask covering a single common line's worth of the 96 bit [`Segments`] value
+const COM_MASK: u128 = u32::MAX as u128;
+
 
 /// Liquid crystal display
 pub struct Lcd(LCD);
@@ -114,16 +117,101 @@ impl Lcd {
 
     /// Write segments to the LCD
     pub fn write(&mut self, seg: Segments) {
-        const MASK: u128 = u32::MAX as u128;
-
         // This is safe assuming that Segments has been correctly created
         unsafe {
-            (*self).ram_com0.as_ptr().write((seg & MASK) as u32);
-            (*self).ram_com1.as_ptr().write((seg >> 32 & MASK) as u32);
-            (*self).ram_com2.as_ptr().write((seg >> 64 & MASK) as u32);
+            (*self).ram_com0.as_ptr().write((seg & COM_MASK) as u32);
+            (*self).ram_com1.as_ptr().write((seg >> 32 & COM_MASK) as u32);
+            (*self).ram_com2.as_ptr().write((seg >> 64 & COM_MASK) as u32);
         }
 
-        // Trigger a display update
+        self.update();
+    }
+
+    /// Trigger a display update
+    pub fn update(&mut self) {
         (*self).sr.modify(|_, w| w.udr().set_bit());
     }
+
+    /// Clear the given segments from the display RAM, leaving the rest untouched.
+    fn clear_segments(&mut self, seg: Segments) {
+        unsafe {
+            let com0 = (*self).ram_com0.as_ptr();
+            com0.write(com0.read() & !((seg & COM_MASK) as u32));
+            let com1 = (*self).ram_com1.as_ptr();
+            com1.write(com1.read() & !((seg >> 32 & COM_MASK) as u32));
+            let com2 = (*self).ram_com2.as_ptr();
+            com2.write(com2.read() & !((seg >> 64 & COM_MASK) as u32));
+        }
+    }
+
+    /// Set the given segments in the display RAM, leaving the rest untouched.
+    fn set_segments(&mut self, seg: Segments) {
+        unsafe {
+            let com0 = (*self).ram_com0.as_ptr();
+            com0.write(com0.read() | (seg & COM_MASK) as u32);
+            let com1 = (*self).ram_com1.as_ptr();
+            com1.write(com1.read() | (seg >> 32 & COM_MASK) as u32);
+            let com2 = (*self).ram_com2.as_ptr();
+            com2.write(com2.read() | (seg >> 64 & COM_MASK) as u32);
+        }
+    }
+
+    /// Replace the glyph at a physical position, clearing it first.
+    fn put(&mut self, pos: usize, seg: Segments) {
+        self.clear_segments(digit::mask(pos));
+        self.set_segments(seg);
+    }
+
+    /// Write a decimal number right-aligned so that its units digit lands on `pos`.
+    pub fn write_number(&mut self, pos: usize, value: u32) {
+        let mut value = value;
+        let mut pos = pos as isize;
+
+        loop {
+            self.put(pos as usize, digit::digit(pos as usize, (value % 10) as u8));
+
+            value /= 10;
+            pos -= 1;
+
+            if value == 0 || pos < 0 {
+                break;
+            }
+        }
+
+        self.update();
+    }
+
+    /// Show or hide the low battery indicator glyph.
+    pub fn write_battery(&mut self, low: bool) {
+        if low {
+            self.set_segments(BATTERY);
+        } else {
+            self.clear_segments(BATTERY);
+        }
+
+        self.update();
+    }
+
+    /// Write a 24 hour time as `HH` `MM` across the four leftmost digits.
+    pub fn write_time(&mut self, hours: u8, minutes: u8) {
+        self.put(0, digit::digit(0, hours / 10));
+        self.put(1, digit::digit(1, hours % 10));
+        self.put(2, digit::digit(2, minutes / 10));
+        self.put(3, digit::digit(3, minutes % 10));
+
+        self.update();
+    }
+
+    /// Write a temperature (in millidegrees) as whole degrees across the rightmost digits, with a
+    /// leading minus sign for sub-zero readings.
+    pub fn write_temperature(&mut self, millideg: i32) {
+        let degrees = millideg / 1000;
+        let magnitude = degrees.unsigned_abs();
+
+        self.put(3, if degrees < 0 { digit::minus(3) } else { BLANK });
+        self.put(4, digit::digit(4, ((magnitude / 10) % 10) as u8));
+        self.put(5, digit::digit(5, (magnitude % 10) as u8));
+
+        self.update();
+    }
 }
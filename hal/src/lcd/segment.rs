@@ -53,6 +53,9 @@ macro_rules! segments {
 /// Turn off all segments
 pub const BLANK: Segments = 0;
 
+/// The low battery indicator glyph
+pub const BATTERY: Segments = build_segment(2, 13);
+
 // 7 segment displays are numbered left (hours) to right (seconds), 0 to 5
 segments! {
     D0_A => (1, 5),
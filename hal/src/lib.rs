@@ -21,6 +21,7 @@
 pub mod adc;
 pub mod buzzer;
 pub mod lcd;
+pub mod mono;
 pub mod rtc;
 pub mod system;
 
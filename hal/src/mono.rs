@@ -0,0 +1,102 @@
+//! # Monotonic timer
+//!
+//! The RTC wakeup interrupt only fires once a second which is enough to refresh the display but
+//! too coarse for general scheduling (debouncing the buttons, timing out a settings-edit mode or
+//! pulsing the buzzer). This module provides a monotonic clock, in the shape expected by
+//! [`rtic_monotonics`], backed by a free running TIM3 compare.
+//!
+//! TIM3 is only 16 bits wide, so an overflow counter is kept alongside it and combined with the
+//! hardware counter to form a 64 bit tick count. Both the update (overflow) and the capture
+//! compare events have to be serviced in the interrupt handler to keep the two halves consistent.
+//!
+//! TIM3 is used (rather than TIM2) so the monotonic can run alongside the [`buzzer`](crate::buzzer),
+//! which owns TIM2; this lets the melody player be scheduled on the monotonic.
+
+use crate::system::{System, CLK_FREQ};
+use core::sync::atomic::{AtomicU32, Ordering};
+use stm32l0::stm32l0x3::TIM3;
+
+/// The monotonic runs directly off the system clock (no prescaling)
+const TIMER_HZ: u32 = CLK_FREQ as u32;
+
+/// An instant on the monotonic timeline
+pub type Instant = fugit::Instant<u64, 1, TIMER_HZ>;
+
+/// A duration on the monotonic timeline
+pub type Duration = fugit::Duration<u64, 1, TIMER_HZ>;
+
+/// The number of times the 16 bit hardware counter has wrapped
+static OVERFLOW: AtomicU32 = AtomicU32::new(0);
+
+/// # Monotonic timer
+///
+/// A free running TIM3 based monotonic clock. See [`crate::mono`] for more information.
+pub struct Mono(TIM3);
+
+impl Mono {
+    /// Configure TIM3 as a free running monotonic counter.
+    pub fn configure(timer: TIM3, sys: &mut System) -> Self {
+        sys.enable_tim3_clk();
+
+        // Free run at the full system clock; the 64 bit tick count is assembled in software.
+        timer.psc.write(|w| w.psc().bits(0));
+        timer.arr.write(|w| w.arr().bits(u16::MAX as u32));
+
+        // Enable the update (overflow) and capture compare 1 interrupts
+        timer.dier.write(|w| w.uie().enabled().cc1ie().enabled());
+
+        // Start counting
+        timer.cr1.modify(|_, w| w.cen().enabled());
+
+        Self(timer)
+    }
+
+    /// Read the current monotonic instant.
+    ///
+    /// The overflow counter is re-read if the hardware counter wrapped during the read so that the
+    /// two halves always agree.
+    pub fn now(&self) -> Instant {
+        let ticks = loop {
+            let hi = OVERFLOW.load(Ordering::Relaxed);
+            let cnt = self.0.cnt.read().cnt().bits() as u64;
+
+            // An overflow which has happened but whose update interrupt hasn't run yet is not
+            // reflected in `OVERFLOW`, so fold the pending wrap in by hand. Re-read the counter
+            // afterwards as it has already rolled past zero.
+            if self.0.sr.read().uif().bit_is_set() {
+                let cnt = self.0.cnt.read().cnt().bits() as u64;
+                break ((hi as u64 + 1) << 16) | cnt;
+            }
+
+            let hi_again = OVERFLOW.load(Ordering::Relaxed);
+            if hi == hi_again {
+                break ((hi as u64) << 16) | cnt;
+            }
+        };
+
+        Instant::from_ticks(ticks)
+    }
+
+    /// Schedule the next compare interrupt at `instant`.
+    pub fn set_compare(&mut self, instant: Instant) {
+        // Only the low 16 bits are programmable, so the compare fires the next time the counter
+        // reaches them, even when `instant` lies in a later overflow period; [`Mono::on_interrupt`]
+        // does not filter such early matches.
+        self.0
+            .ccr1
+            .write(|w| w.ccr().bits(instant.ticks() as u16 as u32));
+    }
+
+    /// Clear the capture compare interrupt flag.
+    pub fn clear_compare_flag(&mut self) {
+        self.0.sr.modify(|_, w| w.cc1if().clear_bit());
+    }
+
+    /// Service the timer interrupt, accounting for counter overflows.
+    pub fn on_interrupt(&mut self) {
+        if self.0.sr.read().uif().bit_is_set() {
+            self.0.sr.modify(|_, w| w.uif().clear_bit());
+            OVERFLOW.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
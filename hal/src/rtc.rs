@@ -11,19 +11,44 @@
 //!
 //! ## Alarms
 //!
-//! TODO
+//! The RTC provides alarm A which can be programmed to fire when the current time matches a set
+//! time. Which fields of the time have to match is selected by an [`AlarmMask`], so an alarm can
+//! either repeat (e.g. match only the minutes and seconds) or be a one-shot (match the full
+//! HH:MM:SS). The alarm event is routed through EXTI line 17 and is intended to start the buzzer.
 //!
 //! ## Backup register
 //!
 //! The RTC contains a register which retains it's contents as long as the RTC is powered; meaning
-//! that it survives a reset. In the watch backup register is used to store the ADC calibration.
+//! that it survives a reset. In the watch backup register is used to store the ADC calibration
+//! and the crystal calibration baseline temperature.
+//!
+//! ## Temperature compensation
+//!
+//! The 32.768 kHz tuning-fork crystal has a parabolic frequency-vs-temperature response, so its
+//! frequency error is well modelled by `Δf/f ≈ -k·(T − T₀)²` with a turnover temperature
+//! `T₀ ≈ 25 °C` and `k ≈ 0.034 ppm/°C²`. [`Rtc::calibrate`] cancels this drift by reprogramming
+//! the smooth-calibration register (`CALR`) from the current die [`Temperature`], turning the
+//! temperature sensor and calibration output into a closed-loop disciplined clock.
 
+use crate::adc::Temperature;
 use crate::system::System;
 use core::marker::PhantomData;
 use stm32l0::stm32l0x3::{EXTI, RTC};
 
 use stm32l0::stm32l0x3::rtc::tr::R as TR_R;
 
+/// Crystal temperature coefficient `-k` scaled by 1000 (`k` = 0.034 ppm/°C²)
+const CRYSTAL_K_MILLI: i32 = -34;
+
+/// Crystal turnover temperature `T₀` (°C)
+const CRYSTAL_T0: u8 = 25;
+
+/// A duration since midnight, measured in whole seconds.
+///
+/// This is the `fugit` representation of a [`Time`]; converting to it lets callers do plain
+/// arithmetic (e.g. "alarm in 90 minutes") instead of hand-rolling BCD carries.
+pub type TimeOfDay = fugit::Duration<u32, 1, 1>;
+
 /// Binary coded decimal represenation of the time
 pub struct Time {
     /// hour tens digit (0-2)
@@ -42,6 +67,76 @@ pub struct Time {
     pub seconds_units: u8,
 }
 
+impl From<Time> for TimeOfDay {
+    /// Convert a BCD time into a duration since midnight
+    fn from(time: Time) -> TimeOfDay {
+        let hours = (time.hour_tens * 10 + time.hour_units) as u32;
+        let minutes = (time.minute_tens * 10 + time.minute_units) as u32;
+        let seconds = (time.seconds_tens * 10 + time.seconds_units) as u32;
+
+        TimeOfDay::from_ticks(hours * 3600 + minutes * 60 + seconds)
+    }
+}
+
+impl From<TimeOfDay> for Time {
+    /// Convert a duration since midnight into a BCD time, wrapping at 24 hours
+    fn from(duration: TimeOfDay) -> Time {
+        let total = duration.ticks() % (24 * 3600);
+
+        let hours = (total / 3600) as u8;
+        let minutes = ((total % 3600) / 60) as u8;
+        let seconds = (total % 60) as u8;
+
+        Time {
+            hour_tens: hours / 10,
+            hour_units: hours % 10,
+
+            minute_tens: minutes / 10,
+            minute_units: minutes % 10,
+
+            seconds_tens: seconds / 10,
+            seconds_units: seconds % 10,
+        }
+    }
+}
+
+/// Selects which fields of the [`Time`] an alarm has to match in order to fire.
+///
+/// A field which is left unmasked (`true`) has to match the corresponding field of the current
+/// time; a masked field (`false`) is treated as "don't care". Matching only the minutes and
+/// seconds therefore gives a repeating per-hour alarm, while matching the hours, minutes and
+/// seconds gives a one-shot daily alarm. The date is always masked as [`Time`] does not carry a
+/// calendar.
+pub struct AlarmMask {
+    /// Match the hour fields
+    pub hours: bool,
+    /// Match the minute fields
+    pub minutes: bool,
+    /// Match the seconds fields
+    pub seconds: bool,
+}
+
+/// Binary coded decimal representation of the date
+pub struct Date {
+    /// year tens digit (0-9)
+    pub year_tens: u8,
+    /// year units digit (0-9)
+    pub year_units: u8,
+
+    /// month tens digit (0-1)
+    pub month_tens: u8,
+    /// month units digit (0-9)
+    pub month_units: u8,
+
+    /// day tens digit (0-3)
+    pub day_tens: u8,
+    /// day units digit (0-9)
+    pub day_units: u8,
+
+    /// weekday (1-7, Monday is 1)
+    pub weekday: u8,
+}
+
 /// RTC initialisation mode
 ///
 /// RTC is stopped; the time registers become writeable allowing the time to be set.
@@ -87,6 +182,15 @@ impl Rtc<Run> {
         exti.emr.modify(|_, w| w.em20().unmasked());
         rtc.cr.modify(|_, w| w.wutie().enabled().wute().enabled());
 
+        // Configure rtc alarm event
+        //
+        // * Enable rising edge trigger
+        // * Unmask alarm event
+        //
+        // The alarm itself is armed later by [`Rtc::set_alarm`].
+        exti.rtsr.modify(|_, w| w.rt17().enabled());
+        exti.emr.modify(|_, w| w.em17().unmasked());
+
         sys.enable_rtc();
 
         Self(rtc, PhantomData)
@@ -125,6 +229,38 @@ impl Rtc<Run> {
         }
     }
 
+    /// Read the date register.
+    ///
+    /// Get the current date
+    ///
+    /// `TR` and `DR` are latched together, so `DR` is bracketed by two reads of `TR`. If the time
+    /// ticked between those reads a midnight rollover could have occured between reading `TR` and
+    /// `DR`, so the read is taken again.
+    pub fn date(&self) -> Date {
+        let dr = loop {
+            let before = self.0.tr.read().su().bits();
+            let dr = self.0.dr.read();
+            let after = self.0.tr.read().su().bits();
+
+            if before == after {
+                break dr;
+            }
+        };
+
+        Date {
+            year_tens: dr.yt().bits(),
+            year_units: dr.yu().bits(),
+
+            month_tens: dr.mt().bit() as u8,
+            month_units: dr.mu().bits(),
+
+            day_tens: dr.dt().bits(),
+            day_units: dr.du().bits(),
+
+            weekday: dr.wdu().bits(),
+        }
+    }
+
     /// Check the wake up timer interrupt flag
     pub fn isr_wakeup(&mut self) -> bool {
         let is_wakeup = self.0.isr.read().wutf().bit_is_set();
@@ -137,6 +273,54 @@ impl Rtc<Run> {
         is_wakeup
     }
 
+    /// Discipline the crystal against temperature drift.
+    ///
+    /// The LSE tuning fork runs slow either side of its turnover temperature following
+    /// `Δf/f ≈ -k·(T − T₀)²`. The matching positive correction is computed from `temp` and written
+    /// to the smooth-calibration register (`CALR`), which over a 2²⁰ LSE cycle (~32 s) window adds
+    /// up to 512 pulses (`CALP`) and masks out up to 511 pulses (`CALM`) for an effective error of
+    /// `(512·CALP − CALM)/2²⁰`.
+    pub fn calibrate(&mut self, temp: &Temperature) {
+        // Lazily seed the turnover baseline into the backup register the first time we calibrate.
+        let mut t0 = self.get_calibration_baseline();
+        if t0 == 0 {
+            t0 = CRYSTAL_T0;
+            self.set_calibration_baseline(t0);
+        }
+
+        // `temp` is in millidegrees; the crystal model works in whole degrees celsius.
+        let delta = **temp / 1000 - t0 as i32;
+
+        // `Δf/f = -k·(T − T₀)²` with `k` positive, so the crystal error runs negative off turnover.
+        let error_ppm = CRYSTAL_K_MILLI * delta * delta / 1000;
+
+        // Apply the opposite correction so the two cancel, expressed as a whole number of pulses
+        // over the 2²⁰ cycle window.
+        let pulses = -error_ppm * (1 << 20) / 1_000_000;
+        let (calp, calm) = if pulses > 0 {
+            (true, (512 - pulses).clamp(0, 511) as u16)
+        } else {
+            (false, (-pulses).clamp(0, 511) as u16)
+        };
+
+        // Wait for any pending re-calibration to be applied before writing new values
+        while self.0.isr.read().recalpf().bit_is_set() {}
+
+        self.0.calr.write(|w| w.calp().bit(calp).calm().bits(calm));
+    }
+
+    /// Check the alarm A interrupt flag
+    pub fn isr_alarm(&mut self) -> bool {
+        let is_alarm = self.0.isr.read().alraf().bit_is_set();
+
+        if is_alarm {
+            // Clear the interrupt flag
+            self.0.isr.modify(|_, w| w.alraf().clear_bit());
+        }
+
+        is_alarm
+    }
+
     /// Enter initialisation mode
     pub fn init(self) -> Rtc<Init> {
         Rtc::from(self)
@@ -163,6 +347,26 @@ impl Rtc<Init> {
         });
     }
 
+    /// Set the RTC to the given date
+    pub fn set_date(&mut self, date: Date) {
+        self.0.dr.write(|w| {
+            w.yt()
+                .bits(date.year_tens)
+                .yu()
+                .bits(date.year_units)
+                .mt()
+                .bit(date.month_tens != 0)
+                .mu()
+                .bits(date.month_units)
+                .dt()
+                .bits(date.day_tens)
+                .du()
+                .bits(date.day_units)
+                .wdu()
+                .bits(date.weekday)
+        });
+    }
+
     /// Enter run mode
     pub fn run(self) -> Rtc<Run> {
         Rtc::from(self)
@@ -194,13 +398,68 @@ impl From<Rtc<Init>> for Rtc<Run> {
 }
 
 impl<S> Rtc<S> {
+    /// Arm alarm A to fire at the given time.
+    ///
+    /// The [`AlarmMask`] selects which fields of `time` have to match for the alarm to fire. Once
+    /// armed, the alarm event is delivered on EXTI line 17 (configured by [`Rtc::configure`]) and
+    /// the interrupt flag can be read and cleared with [`Rtc::isr_alarm`].
+    pub fn set_alarm(&mut self, time: Time, mask: AlarmMask) {
+        // The alarm registers are write protected while the alarm is enabled, so disable it first
+        // and wait for the write flag to be set.
+        self.0.cr.modify(|_, w| w.alrae().disabled());
+        while self.0.isr.read().alrawf().bit_is_clear() {}
+
+        // Write the match time and mask bits. A set mask bit marks the field as "don't care"; the
+        // date is always masked as the alarm only matches the time of day.
+        self.0.alrmar.write(|w| {
+            w.msk4()
+                .set_bit()
+                .msk3()
+                .bit(!mask.hours)
+                .ht()
+                .bits(time.hour_tens)
+                .hu()
+                .bits(time.hour_units)
+                .msk2()
+                .bit(!mask.minutes)
+                .mnt()
+                .bits(time.minute_tens)
+                .mnu()
+                .bits(time.minute_units)
+                .msk1()
+                .bit(!mask.seconds)
+                .st()
+                .bits(time.seconds_tens)
+                .su()
+                .bits(time.seconds_units)
+        });
+
+        // Enable the alarm interrupt and re-arm the alarm
+        self.0.cr.modify(|_, w| w.alraie().enabled().alrae().enabled());
+    }
+
     /// Write ADC calibration to RTC backup register 0
+    ///
+    /// The calibration is stored in the low byte; the upper bytes hold the crystal calibration
+    /// baseline and are left untouched.
     pub(crate) fn set_adc_calibration(&mut self, calibration: u8) {
-        self.0.bkpr[0].write(|w| w.bkp().bits(calibration as u32));
+        self.0.bkpr[0]
+            .modify(|r, w| w.bkp().bits((r.bkp().bits() & !0xFF) | calibration as u32));
     }
 
     /// Read ADC calibration to RTC backup register 0
     pub(crate) fn get_adc_calibration(&self) -> u8 {
         self.0.bkpr[0].read().bkp().bits() as u8
     }
+
+    /// Write the crystal calibration baseline temperature (°C) to RTC backup register 0
+    pub(crate) fn set_calibration_baseline(&mut self, t0: u8) {
+        self.0.bkpr[0]
+            .modify(|r, w| w.bkp().bits((r.bkp().bits() & !(0xFF << 8)) | ((t0 as u32) << 8)));
+    }
+
+    /// Read the crystal calibration baseline temperature (°C) from RTC backup register 0
+    pub(crate) fn get_calibration_baseline(&self) -> u8 {
+        (self.0.bkpr[0].read().bkp().bits() >> 8) as u8
+    }
 }
@@ -4,6 +4,21 @@ use stm32l0::stm32l0x3::{PWR, RCC};
 /// The system clock frequency (Hz)
 pub const CLK_FREQ: usize = 65536;
 
+/// Selects which events are allowed to wake the MCU from [`Standby`](System::enter_standby).
+///
+/// Standby resets the core and disables the EXTI GPIO lines, so a button can only wake the MCU
+/// when it sits on one of the dedicated WKUP pins. On this board the only usable WKUP pin is
+/// WKUP2 (PC13) — WKUP1 (PA0) drives the buzzer and WKUP3 (PE6) is not broken out — so `button`
+/// refers to a wake button on PC13, not the PA2/PB9 mode and alarm buttons. The RTC and LSE keep
+/// running in standby, so an armed RTC alarm wakes the watch regardless.
+pub struct WakeSources {
+    /// Wake on an RTC alarm. [`System::enter_standby`] does not act on this flag: an armed RTC
+    /// alarm wakes the MCU whether it is set or not.
+    pub rtc_alarm: bool,
+    /// Wake on a press of the WKUP2 (PC13) button
+    pub button: bool,
+}
+
 /// # System management
 ///
 /// The general clock and power configuration is such that to provide ultra low power operation
@@ -14,7 +29,7 @@ pub const CLK_FREQ: usize = 65536;
 /// Note that the LPRUN mode isn't used as it would require a full reset after each wakeup from
 /// stop. As the device is designed to constantly be entering and exiting stop mode, using
 /// LPRUN isn't feasible.
-pub struct System(RCC);
+pub struct System(RCC, bool);
 
 impl System {
     pub fn configure(rcc: RCC, pwr: &mut PWR, scb: &mut SCB) -> Self {
@@ -27,6 +42,12 @@ impl System {
         // Enable PWR clock
         rcc.apb1enr.modify(|_, w| w.pwren().enabled());
 
+        // Detect and clear a standby wake so the caller can restore the watch face
+        let woke_from_standby = pwr.csr.read().sbf().bit_is_set();
+        if woke_from_standby {
+            pwr.cr.modify(|_, w| w.csbf().set_bit());
+        }
+
         // Configure PWR control register
         //
         // * Enable voltage regulator range 3 (1.2V)
@@ -64,7 +85,41 @@ impl System {
         // Wait for the LSE to stabilise
         while rcc.csr.read().lserdy().is_not_ready() {}
 
-        Self(rcc)
+        Self(rcc, woke_from_standby)
+    }
+
+    /// Enter standby mode for the lowest possible current draw.
+    ///
+    /// Standby powers down the core and most peripherals but keeps the RTC and LSE alive, so the
+    /// watch still keeps time and can wake on a set alarm. `wake` selects the permitted wake
+    /// sources. Waking from standby resets the MCU, so this function never returns; use
+    /// [`System::woke_from_standby`] after the next [`System::configure`] to restore the watch
+    /// face.
+    pub fn enter_standby(&mut self, pwr: &mut PWR, wake: WakeSources) -> ! {
+        // Select standby mode on deep sleep and clear any stale wakeup flag
+        pwr.cr.modify(|_, w| w.pdds().standby_mode().cwuf().set_bit());
+
+        // An armed RTC alarm wakes the watch through the backup domain with no extra PWR setup.
+        // `rtc_alarm` is not acted on: the alarm is neither enabled nor masked here, so an armed
+        // alarm wakes the watch even when `rtc_alarm` is false.
+        let _ = wake.rtc_alarm;
+
+        // The WKUP2 (PC13) pin is the only button wake line available on this board (see
+        // [`WakeSources`]); enable or disable it to match the request.
+        pwr.csr.modify(|_, w| w.ewup2().bit(wake.button));
+
+        // SLEEPDEEP is already set by [`System::configure`]; standby is entered on WFI.
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+
+    /// Whether the MCU has just reset out of standby.
+    ///
+    /// The flag is latched by [`System::configure`], allowing the caller to restore the watch
+    /// face rather than performing a cold start.
+    pub fn woke_from_standby(&self) -> bool {
+        self.1
     }
 
     /// Enable the ADC peripheral clock (PCLK)
@@ -99,4 +154,12 @@ impl System {
         // Disable TIM2 clock during sleep
         self.0.apb1smenr.modify(|_, w| w.tim2smen().disabled());
     }
+
+    /// Enable TIM3 peripheral clock
+    pub(crate) fn enable_tim3_clk(&mut self) {
+        self.0.apb1enr.modify(|_, w| w.tim3en().enabled());
+
+        // Disable TIM3 clock during sleep
+        self.0.apb1smenr.modify(|_, w| w.tim3smen().disabled());
+    }
 }
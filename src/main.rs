@@ -15,8 +15,9 @@ mod rtc;
     dispatchers = [I2C1, I2C2, I2C3]
 )]
 mod app {
-    use crate::measurement::{Temperature, Voltage};
+    use crate::measurement::{StateOfCharge, Temperature, Voltage};
     use crate::buzzer::Buzzer;
+    use crate::rtc::Alarm;
 
     use cortex_m::peripheral::SCB;
     use stm32l0xx_hal::prelude::*;
@@ -32,6 +33,22 @@ mod app {
 
     use rtic_monotonics::systick::{fugit::ExtU32, Systick};
 
+    /// Crystal temperature coefficient `k` scaled by 1000 (-0.034 ppm/°C²)
+    const CRYSTAL_K_MILLI: i32 = -34;
+
+    /// Crystal turnover temperature `T0` (°C)
+    const CRYSTAL_T0: i32 = 25;
+
+    /// State of charge (%) below which the low battery warning is shown
+    const LOW_BATTERY_THRESHOLD: u8 = 15;
+
+    /// Alarm time armed at power on, and re-armed each time the mode button turns the alarm on
+    const DEFAULT_ALARM: Alarm = Alarm {
+        hours: 7,
+        minutes: 0,
+        weekday: None,
+    };
+
 
     #[shared]
     struct Shared {
@@ -95,6 +112,10 @@ mod app {
 
         use crate::rtc::RtcInt as _;
         rtc.enable_wakeup_interrupt(&mut exti);
+        rtc.enable_alarm_interrupt(&mut exti);
+
+        // Arm a default alarm; the mode button disarms it and arms it again
+        rtc.set_alarm(DEFAULT_ALARM);
 
         // Start wakeup timer to update watch face every second
         rtc.wakeup_timer().start(1_u32);
@@ -143,19 +164,25 @@ mod app {
     fn wakeup(cx: wakeup::Context) {
         defmt::info!("rtc wakeup");
 
+        use crate::rtc::RtcInt as _;
+
         let mut time = rtc::NaiveDateTime::default();
         let mut rtc = cx.shared.rtc;
+        let mut alarm_fired = false;
 
+        // The wakeup timer and alarm A share the RTC interrupt, so demux on their flags.
         rtc.lock(|rtc| {
-            // Clear interrupt
-            rtc.wakeup_timer().wait().unwrap();
+            // Clear the wakeup interrupt
+            let _ = rtc.wakeup_timer().wait();
+            alarm_fired = rtc.check_alarm();
             time = rtc.now();
         });
 
         use stm32l0xx_hal::rtc::Timelike as _;
 
-        // Sound the buzzer on the top of the hour if enabled
-        if *cx.shared.buzzer_enabled && time.minute() == 0 && time.second() == 0 {
+        // Sound the buzzer on the alarm, or on the top of the hour if enabled
+        let hourly_chime = time.minute() == 0 && time.second() == 0;
+        if *cx.shared.buzzer_enabled && (alarm_fired || hourly_chime) {
             if let Err(_) = beep::spawn() {
                 defmt::error!("unable to spawn beep, already running");
             }
@@ -176,6 +203,30 @@ mod app {
         *buzzer_enabled = !*buzzer_enabled;
     }
 
+    /// Toggle the alarm on and off every time the mode button is pressed.
+    ///
+    /// This is the entry point for the alarm-setting UI: for now it simply arms [`DEFAULT_ALARM`]
+    /// or disarms alarm A, reusing the same set/clear/query API a richer editor would drive.
+    #[task(binds = EXTI4_15, shared = [rtc])]
+    fn mode_btn(cx: mode_btn::Context) {
+        defmt::info!("mode button");
+
+        // Clear the interrupt (mode button is on PB9)
+        use stm32l0xx_hal::exti::ExtiLine as _;
+        Exti::unpend(GpioLine::from_raw_line(9).unwrap());
+
+        use crate::rtc::RtcInt as _;
+
+        let mut rtc = cx.shared.rtc;
+        rtc.lock(|rtc| {
+            if rtc.alarm_enabled() {
+                rtc.clear_alarm();
+            } else {
+                rtc.set_alarm(DEFAULT_ALARM);
+            }
+        });
+    }
+
     #[task(priority = 2, local = [buzzer])]
     async fn beep(cx: beep::Context) {
         defmt::info!("beep");
@@ -191,8 +242,10 @@ mod app {
     async fn calibrate(cx: calibrate::Context) {
         defmt::info!("calibrate rtc");
         let adc = cx.local.adc;
+        let mut rtc = cx.shared.rtc;
 
         let mut vtemp = VTemp::new();
+        let mut vref = VRef::new();
 
         loop {
             defmt::info!("starting an rtc calibration");
@@ -204,7 +257,31 @@ mod app {
 
             vtemp.disable(adc);
 
-            // XXX: Implement setting the rtc calibration values in the hal
+            // Read the cell voltage alongside the temperature to drive the battery fuel gauge
+            vref.enable(adc);
+            let voltage: Voltage = adc.read(&mut vref).unwrap();
+            vref.disable(adc);
+
+            let millivolts = *voltage;
+            let soc = StateOfCharge::from(voltage);
+            defmt::info!("Battery {}% ({}mV)", *soc, millivolts);
+
+            // Warn the user while there is still charge left to replace the cell
+            if *soc < LOW_BATTERY_THRESHOLD {
+                defmt::warn!("battery low");
+                if let Err(_) = beep::spawn() {
+                    defmt::error!("unable to spawn beep, already running");
+                }
+            }
+
+            // The 32.768 kHz tuning fork has a parabolic frequency response around its turnover
+            // temperature, Δf/f(ppm) ≈ k·(T − T0)² with k ≈ -0.034 ppm/°C² and T0 ≈ 25 °C. Discipline
+            // the LSE by feeding the resulting error to the smooth calibration register.
+            let delta = *temp as i32 - CRYSTAL_T0;
+            let ppm = CRYSTAL_K_MILLI * delta * delta / 1000;
+
+            use crate::rtc::RtcInt as _;
+            rtc.lock(|rtc| rtc.set_smooth_calibration(ppm));
 
             Systick::delay(ExtU32::minutes(15)).await;
         }
@@ -31,14 +31,14 @@ impl Default for Temperature {
 }
 
 
-/// An ADC voltage reading
+/// An ADC voltage reading in millivolts
 pub struct Voltage(u16);
 
 impl From<u16> for Voltage {
-    /// Convert the raw ADC value into volts
+    /// Convert the raw ADC value into millivolts
     fn from(raw: u16) -> Self {
         let vrefint_cal = VrefintCal::get();
-        Self(3 * vrefint_cal.read() / raw)
+        Self((3000u32 * vrefint_cal.read() as u32 / raw as u32) as u16)
     }
 }
 
@@ -55,3 +55,42 @@ impl Default for Voltage {
         Self(0)
     }
 }
+
+
+/// Coin cell discharge curve as (millivolts, state of charge %) break points, ordered from full
+/// to empty. A CR2032 holds ~3.0 V when full, sags to ~2.9 V around the half way point and is
+/// considered flat by ~2.5 V.
+const DISCHARGE_CURVE: [(u16, u8); 3] = [(3000, 100), (2900, 50), (2500, 0)];
+
+/// A battery state of charge as a percentage (0-100)
+pub struct StateOfCharge(u8);
+
+impl From<Voltage> for StateOfCharge {
+    /// Map a cell voltage through the discharge curve, interpolating between break points
+    fn from(voltage: Voltage) -> Self {
+        let mv = *voltage;
+
+        if mv >= DISCHARGE_CURVE[0].0 {
+            return Self(DISCHARGE_CURVE[0].1);
+        }
+
+        for pair in DISCHARGE_CURVE.windows(2) {
+            let (v_hi, soc_hi) = pair[0];
+            let (v_lo, soc_lo) = pair[1];
+
+            if mv >= v_lo {
+                return Self(soc_lo + ((soc_hi - soc_lo) as u16 * (mv - v_lo) / (v_hi - v_lo)) as u8);
+            }
+        }
+
+        Self(0)
+    }
+}
+
+impl Deref for StateOfCharge {
+    type Target = u8;
+
+    fn deref(&self) -> &u8 {
+        &self.0
+    }
+}
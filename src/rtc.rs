@@ -1,9 +1,50 @@
 use stm32l0xx_hal::exti::{Exti, ConfigurableLine, TriggerEdge};
+use stm32l0xx_hal::pac::RTC;
 use stm32l0xx_hal::rtc::{Rtc, Interrupts};
 
+/// A time-of-day alarm.
+///
+/// The alarm fires when the hours and minutes match. An optional `weekday` (1-7, Monday is 1)
+/// restricts the alarm to a single day of the week; leaving it `None` makes the alarm repeat
+/// every day.
+#[derive(Clone, Copy)]
+pub struct Alarm {
+    pub hours: u8,
+    pub minutes: u8,
+    pub weekday: Option<u8>,
+}
+
+/// Encode a value as its BCD tens and units nibbles
+fn bcd(value: u8) -> (u8, u8) {
+    (value / 10, value % 10)
+}
+
 pub trait RtcInt {
     /// Enable interrupts for the RTC wakeup timer
     fn enable_wakeup_interrupt(&mut self, exti: &mut Exti);
+
+    /// Enable interrupts for RTC alarm A
+    fn enable_alarm_interrupt(&mut self, exti: &mut Exti);
+
+    /// Program and arm alarm A
+    fn set_alarm(&mut self, alarm: Alarm);
+
+    /// Disarm alarm A
+    fn clear_alarm(&mut self);
+
+    /// Whether alarm A is currently armed
+    fn alarm_enabled(&self) -> bool;
+
+    /// Check and clear the alarm A interrupt flag
+    fn check_alarm(&mut self) -> bool;
+
+    /// Apply smooth calibration to cancel a measured frequency error.
+    ///
+    /// `ppm` is the crystal's current error in parts per million (negative when it runs slow).
+    /// The matching correction is written to the `CALR` register over the 2²⁰ LSE cycle (~32 s)
+    /// smooth-calibration window, where `CALP` adds 512 pulses and `CALM[8:0]` masks out up to
+    /// 511, giving a net correction of `(512·CALP − CALM)/2²⁰`.
+    fn set_smooth_calibration(&mut self, ppm: i32);
 }
 
 impl RtcInt for Rtc {
@@ -16,4 +57,112 @@ impl RtcInt for Rtc {
         // Listen for RTC wakeup timer interrupt requests
         exti.listen_configurable(ConfigurableLine::RtcWakeup, TriggerEdge::Rising);
     }
+
+    fn enable_alarm_interrupt(&mut self, exti: &mut Exti) {
+        self.enable_interrupts(Interrupts {
+            alarm_a: true,
+            ..Interrupts::default()
+        });
+
+        // Listen for RTC alarm A interrupt requests on its dedicated EXTI line
+        exti.listen_configurable(ConfigurableLine::RtcAlarm, TriggerEdge::Rising);
+    }
+
+    fn set_alarm(&mut self, alarm: Alarm) {
+        // The alarm registers are write protected, so mirror the register handling of the
+        // stm32l4xx-hal driver: unlock, disable the alarm, program the match and re-enable it.
+        let rtc = unsafe { &*RTC::ptr() };
+
+        rtc.wpr.write(|w| w.key().bits(0xCA));
+        rtc.wpr.write(|w| w.key().bits(0x53));
+
+        rtc.cr.modify(|_, w| w.alrae().clear_bit());
+        while rtc.isr.read().alrawf().bit_is_clear() {}
+
+        let (ht, hu) = bcd(alarm.hours);
+        let (mnt, mnu) = bcd(alarm.minutes);
+        let (dt, du) = alarm.weekday.map(bcd).unwrap_or((0, 0));
+
+        rtc.alrmar.write(|w| unsafe {
+            w.msk4()
+                .bit(alarm.weekday.is_none())
+                .wdsel()
+                .bit(alarm.weekday.is_some())
+                .dt()
+                .bits(dt)
+                .du()
+                .bits(du)
+                // The hours and minutes always take part in the match
+                .msk3()
+                .clear_bit()
+                .ht()
+                .bits(ht)
+                .hu()
+                .bits(hu)
+                .msk2()
+                .clear_bit()
+                .mnt()
+                .bits(mnt)
+                .mnu()
+                .bits(mnu)
+                // Fire at the top of the minute (the seconds have to match 00)
+                .msk1()
+                .clear_bit()
+                .st()
+                .bits(0)
+                .su()
+                .bits(0)
+        });
+
+        rtc.cr.modify(|_, w| w.alrae().set_bit());
+        rtc.wpr.write(|w| w.key().bits(0xFF));
+    }
+
+    fn clear_alarm(&mut self) {
+        let rtc = unsafe { &*RTC::ptr() };
+
+        rtc.wpr.write(|w| w.key().bits(0xCA));
+        rtc.wpr.write(|w| w.key().bits(0x53));
+        rtc.cr.modify(|_, w| w.alrae().clear_bit());
+        rtc.wpr.write(|w| w.key().bits(0xFF));
+    }
+
+    fn alarm_enabled(&self) -> bool {
+        let rtc = unsafe { &*RTC::ptr() };
+        rtc.cr.read().alrae().bit_is_set()
+    }
+
+    fn check_alarm(&mut self) -> bool {
+        let rtc = unsafe { &*RTC::ptr() };
+
+        let fired = rtc.isr.read().alraf().bit_is_set();
+        if fired {
+            rtc.isr.modify(|_, w| w.alraf().clear_bit());
+        }
+
+        fired
+    }
+
+    fn set_smooth_calibration(&mut self, ppm: i32) {
+        let rtc = unsafe { &*RTC::ptr() };
+
+        // Cancel the measured error and express it as a whole number of pulses over the window.
+        let pulses = -ppm * (1 << 20) / 1_000_000;
+        let (calp, calm) = if pulses > 0 {
+            (true, (512 - pulses).clamp(0, 511))
+        } else {
+            (false, (-pulses).clamp(0, 511))
+        };
+
+        rtc.wpr.write(|w| w.key().bits(0xCA));
+        rtc.wpr.write(|w| w.key().bits(0x53));
+
+        // Wait for any pending re-calibration to complete before writing new values
+        while rtc.isr.read().recalpf().bit_is_set() {}
+
+        rtc.calr
+            .write(|w| unsafe { w.calp().bit(calp).calm().bits(calm as u16) });
+
+        rtc.wpr.write(|w| w.key().bits(0xFF));
+    }
 }